@@ -54,9 +54,34 @@ interface QueryData {
   uniqueEventId: string;
   categoryId: string;
   status: { commitPhase?: Record<string, never>; revealPhase?: Record<string, never> };
-  format: { binary?: Record<string, never>; score?: Record<string, never>; decimal?: Record<string, never>; string?: Record<string, never>; optionIndex?: Record<string, never> };
+  format: { binary?: Record<string, never>; score?: Record<string, never>; decimal?: Record<string, never>; string?: Record<string, never>; optionIndex?: Record<string, never>; vector?: Record<string, never> };
+  spec: ResponseSpec;
+  fields: FieldSpec[];
   commitDeadline: BN;
   revealDeadline: BN;
+  round: number;
+  roundSlot: BN;
+}
+
+interface ResponseSpec {
+  scoreMin: BN;
+  scoreMax: BN;
+  decimals: number;
+  optionCount: number;
+  tolerance: BN;
+  normalization: StringNormalization;
+}
+
+interface FieldSpec {
+  format: QueryData["format"];
+  spec: ResponseSpec;
+}
+
+interface StringNormalization {
+  caseFold: boolean;
+  nfc: boolean;
+  collapseWhitespace: boolean;
+  trim: boolean;
 }
 
 // ============================================
@@ -65,46 +90,133 @@ interface QueryData {
 
 /**
  * Normalize raw data into expected format
- * MUST match on-chain format expectations
+ * MUST match on-chain `validate_response`: canonical encodings only, or the reveal is rejected
  */
-function normalizeData(rawData: any, format: any): string {
+function normalizeData(rawData: any, format: any, spec: ResponseSpec, fields: FieldSpec[] = []): string {
   const formatKey = Object.keys(format)[0];
 
   switch (formatKey) {
+    case "vector": {
+      // One raw value per declared field, joined by the ASCII unit separator
+      if (!Array.isArray(rawData) || rawData.length !== fields.length) throw new Error("Invalid Vector");
+      return fields.map((field, i) => normalizeData(rawData[i], field.format, field.spec)).join(VECTOR_SEPARATOR);
+    }
     case "binary": {
       const s = String(rawData).toUpperCase().trim();
-      if (["TRUE", "1", "YES", "Y"].includes(s)) return "YES";
-      return "NO";
+      if (["TRUE", "1", "YES", "Y"].includes(s)) return "1";
+      return "0";
     }
     case "optionIndex": {
       const idx = parseInt(String(rawData));
-      if (isNaN(idx)) throw new Error("Invalid Index");
+      if (isNaN(idx) || idx < 0 || idx >= spec.optionCount) throw new Error("Invalid Index");
       return idx.toString();
     }
     case "decimal": {
-      // Store as integer cents (e.g., $123.45 -> "12345")
-      return Math.round(parseFloat(rawData) * 100).toString();
+      // Exactly spec.decimals fractional digits (e.g., 2 -> "123.45"), never "-0.00"
+      const value = parseFloat(rawData);
+      if (isNaN(value)) throw new Error("Invalid Decimal");
+      const fixed = value.toFixed(spec.decimals);
+      return /^-0(\.0*)?$/.test(fixed) ? fixed.slice(1) : fixed;
     }
     case "score": {
-      return Math.round(parseFloat(rawData)).toString();
+      const score = Math.round(parseFloat(rawData));
+      if (isNaN(score)) throw new Error("Invalid Score");
+      if (score < spec.scoreMin.toNumber() || score > spec.scoreMax.toNumber()) {
+        throw new Error("Score out of range");
+      }
+      return (score === 0 ? 0 : score).toString();
     }
+    case "string":
+      // Commit the canonical form so the option bucket PDA matches the on-chain one
+      return applyNormalization(String(rawData), spec.normalization);
     default:
       return String(rawData).trim();
   }
 }
 
+/**
+ * Mirrors on-chain `StringNormalization::apply`: NFD + case fold + NFC (or NFC alone), collapse, trim.
+ * JS has no case folding, so upper- then lowercasing stands in for it ("ß" -> "ss", "ς" -> "σ");
+ * the commitment covers the raw answer and the program canonicalizes it, so the chain decides.
+ */
+function applyNormalization(value: string, n: StringNormalization): string {
+  let out = value;
+  if (n.caseFold) {
+    if (n.nfc) out = out.normalize("NFD");
+    out = out.toUpperCase().toLowerCase();
+  }
+  if (n.nfc) out = out.normalize("NFC");
+  if (n.collapseWhitespace) out = out.replace(/\p{White_Space}+/gu, " ");
+  if (n.trim) out = out.replace(/^\p{White_Space}+|\p{White_Space}+$/gu, "");
+  return out;
+}
+
+const VECTOR_SEPARATOR = "\x1f";
+const COMMIT_HASH_VERSION = 2;
+const COMMIT_HASH_DOMAIN = "truth_pool:commit";
+
 /**
  * Compute vote hash using keccak256
- * MUST match on-chain: keccak256(value_bytes || salt_bytes)
+ * MUST match on-chain `commitment_hash` (scheme v2):
+ * keccak256(domain || version || query || minerProfile || round_le
+ *           || len_le(value) || value || len_le(salt) || salt)
  */
-function computeVoteHash(value: string, salt: string): Uint8Array {
+function computeVoteHash(
+  query: PublicKey,
+  minerProfile: PublicKey,
+  round: number,
+  value: string,
+  salt: string
+): Uint8Array {
+  const u32le = (n: number) => {
+    const buf = Buffer.alloc(4);
+    buf.writeUInt32LE(n);
+    return buf;
+  };
+  const valueBytes = Buffer.from(value, "utf-8");
+  const saltBytes = Buffer.from(salt, "utf-8");
   const preimage = Buffer.concat([
-    Buffer.from(value, "utf-8"),
-    Buffer.from(salt, "utf-8"),
+    Buffer.from(COMMIT_HASH_DOMAIN, "utf-8"),
+    Buffer.from([COMMIT_HASH_VERSION]),
+    query.toBuffer(),
+    minerProfile.toBuffer(),
+    u32le(round),
+    u32le(valueBytes.length),
+    valueBytes,
+    u32le(saltBytes.length),
+    saltBytes,
   ]);
   return keccak_256(preimage);
 }
 
+/**
+ * Option bucket PDA of a canonical answer, or of one field of a vector answer
+ * [b"option", query, round_le, round_slot_le, field, keccak256(value)]
+ */
+function optionBucketPDA(
+  programId: PublicKey,
+  query: PublicKey,
+  round: number,
+  roundSlot: BN,
+  field: number,
+  value: string
+): PublicKey {
+  const roundBuf = Buffer.alloc(4);
+  roundBuf.writeUInt32LE(round);
+  const [bucket] = PublicKey.findProgramAddressSync(
+    [
+      Buffer.from("option"),
+      query.toBuffer(),
+      roundBuf,
+      roundSlot.toArrayLike(Buffer, "le", 8),
+      Buffer.from([field]),
+      Buffer.from(keccak_256(Buffer.from(value, "utf-8"))),
+    ],
+    programId
+  );
+  return bucket;
+}
+
 /**
  * Load or create wallet
  */
@@ -192,7 +304,12 @@ async function derivePDAs(
     programId
   );
 
-  return { minerProfile, categoryStats, voterRecord };
+  const [membership] = PublicKey.findProgramAddressSync(
+    [Buffer.from("membership"), minerProfile.toBuffer(), Buffer.from(categoryId)],
+    programId
+  );
+
+  return { minerProfile, categoryStats, membership, voterRecord };
 }
 
 // ============================================
@@ -288,40 +405,48 @@ async function runCommitCycle(
       // Normalize answer
       let finalAnswer: string;
       try {
-        finalAnswer = normalizeData(rawAnswer, data.format);
+        finalAnswer = normalizeData(rawAnswer, data.format, data.spec, data.fields);
       } catch (e) {
         console.error(`  Format error: ${e}`);
         continue;
       }
 
-      // Generate salt and compute hash
-      const salt = uuidv4();
-      const voteHash = computeVoteHash(finalAnswer, salt);
-
-      console.log(`  Answer: ${finalAnswer}`);
-      console.log(`  Hash: ${Buffer.from(voteHash).toString("hex").slice(0, 16)}...`);
-
       // Derive PDAs
-      const { minerProfile, categoryStats, voterRecord } = await derivePDAs(
+      const { minerProfile, categoryStats, membership, voterRecord } = await derivePDAs(
         program.programId,
         keypair.publicKey,
         query.publicKey,
         data.categoryId
       );
 
+      // Generate salt and compute hash (bound to this query, round and miner)
+      const salt = uuidv4();
+      const voteHash = computeVoteHash(
+        query.publicKey,
+        minerProfile,
+        data.round,
+        finalAnswer,
+        salt
+      );
+
+      console.log(`  Answer: ${finalAnswer}`);
+      console.log(`  Hash: ${Buffer.from(voteHash).toString("hex").slice(0, 16)}...`);
+
       // Execute commit with retry
       await withRetry(
         () =>
           program.methods
             .commitVote(
               Array.from(voteHash) as any, // [u8; 32]
-              Buffer.from([]) // Empty encrypted salt (simplified - no Lit Protocol)
+              Buffer.from([]), // Empty encrypted salt (simplified - no Lit Protocol)
+              COMMIT_HASH_VERSION
             )
             .accounts({
               voter: keypair.publicKey,
               minerProfile: minerProfile,
               queryAccount: query.publicKey,
               categoryStats: categoryStats,
+              membership: membership,
               voterRecord: voterRecord,
               systemProgram: PublicKey.default,
             })
@@ -396,6 +521,17 @@ async function runRevealCycle(
         program.programId
       );
 
+      // Whole-answer bucket, plus one bucket per field for vector answers
+      const bucketPDA = (field: number, value: string) =>
+        optionBucketPDA(program.programId, query.publicKey, data.round, data.roundSlot, field, value);
+      const fieldBuckets = data.format.vector
+        ? cached.answer.split(VECTOR_SEPARATOR).map((part, i) => ({
+            pubkey: bucketPDA(i + 1, part),
+            isWritable: true,
+            isSigner: false,
+          }))
+        : [];
+
       // Execute reveal with retry
       await withRetry(
         () =>
@@ -407,7 +543,10 @@ async function runRevealCycle(
               queryAccount: query.publicKey,
               voterRecord: voterRecord,
               voteStats: voteStats,
+              optionBucket: bucketPDA(0, cached.answer),
+              systemProgram: PublicKey.default,
             })
+            .remainingAccounts(fieldBuckets)
             .rpc(),
         "Reveal"
       );
@@ -21,8 +21,34 @@ interface MarketConfig {
   category: string;
   bounty?: number;
   format?: number; // 0=Binary, 1=Score, 2=Decimal, 3=String, 4=OptionIndex
+  spec?: ResponseSpec; // Required for Score, Decimal and OptionIndex
 }
 
+interface ResponseSpec {
+  scoreMin: BN;
+  scoreMax: BN;
+  decimals: number;
+  optionCount: number;
+  tolerance: BN;
+  normalization: StringNormalization;
+}
+
+interface StringNormalization {
+  caseFold: boolean;
+  nfc: boolean;
+  collapseWhitespace: boolean;
+  trim: boolean;
+}
+
+const EMPTY_SPEC: ResponseSpec = {
+  scoreMin: new BN(0),
+  scoreMax: new BN(0),
+  decimals: 0,
+  optionCount: 0,
+  tolerance: new BN(0),
+  normalization: { caseFold: false, nfc: false, collapseWhitespace: false, trim: false },
+};
+
 // ============================================
 // UTILITIES
 // ============================================
@@ -121,6 +147,7 @@ async function fetchTodaysEvents(): Promise<MarketConfig[]> {
       id: `ETH-PRICE-${today}`,
       category: "CRYPTO",
       format: 2, // Decimal: ETH price
+      spec: { ...EMPTY_SPEC, decimals: 2, tolerance: new BN(100) }, // +/- $1.00 of the median
     },
   ];
 }
@@ -232,16 +259,18 @@ async function main() {
       // Create market with retry
       const bounty = event.bounty || DEFAULT_BOUNTY;
       const format = event.format ?? 0;
+      const spec = event.spec ?? EMPTY_SPEC;
 
       await withRetry(
         () =>
           (program.methods as any)
-            .requestData(event.id, event.category, new BN(bounty), format)
+            .requestData(event.id, event.category, new BN(bounty), format, spec, null, [])
             .accounts({
               requester: keypair.publicKey,
               categoryStats: categoryStats,
               queryAccount: queryAccount,
               voteStats: voteStats,
+              queryOptions: null, // Only for OptionIndex queries with a declared option list
               systemProgram: PublicKey.default,
             })
             .rpc(),
@@ -0,0 +1,391 @@
+import { AccountMeta, Connection, Keypair, PublicKey, SYSVAR_SLOT_HASHES_PUBKEY } from "@solana/web3.js";
+import { Program, AnchorProvider, Wallet, BN } from "@coral-xyz/anchor";
+import { keccak_256 } from "@noble/hashes/sha3";
+import * as fs from "fs";
+import IDL from "../../target/idl/truth_pool.json";
+
+// ============================================
+// CONFIGURATION
+// ============================================
+
+const RPC_URL = process.env.RPC_URL || "https://api.devnet.solana.com";
+const WALLET_PATH = process.env.WALLET_PATH || "keeper_id.json";
+
+const CONFIG = {
+  pollIntervalMs: 30000, // 30 seconds
+  bucketsPerTx: 20, // Option buckets passed to one tally_votes call
+  maxTallyCalls: 50, // Per query and cycle, in case the tally can't make progress
+};
+
+const WHOLE_ANSWER = 0;
+const VECTOR_SEPARATOR = "\x1f";
+
+// ============================================
+// TYPES
+// ============================================
+
+type Format = { binary?: object; score?: object; decimal?: object; string?: object; optionIndex?: object; vector?: object };
+
+interface ResponseSpec {
+  scoreMin: BN;
+  scoreMax: BN;
+  decimals: number;
+  optionCount: number;
+  tolerance: BN;
+}
+
+interface TypedResult {
+  kind: { none?: object; binary?: object; number?: object; optionIndex?: object };
+  value: BN;
+  exponent: number;
+}
+
+interface QueryData {
+  typedResult: TypedResult;
+  uniqueEventId: string;
+  categoryId: string;
+  status: { revealPhase?: object; settling?: object; finalized?: object };
+  format: Format;
+  spec: ResponseSpec;
+  fields: { format: Format; spec: ResponseSpec }[];
+  revealDeadline: BN;
+  result: string;
+  fieldResults: { value: string; typedResult: TypedResult }[];
+  hasOptionLabels: boolean;
+  randomAccumulator: number[];
+  round: number;
+  roundSlot: BN;
+  winningWeight: BN;
+  lotterySlot: BN;
+}
+
+interface TallyProgress {
+  stage: { pending?: object; scan?: object; settle?: object };
+  step: number;
+  processed: number;
+}
+
+interface Bucket {
+  pubkey: PublicKey;
+  field: number;
+  value: string;
+  weight: BN;
+  ticketOffset: BN;
+  key: Buffer;
+}
+
+// ============================================
+// UTILITIES
+// ============================================
+
+/**
+ * Mantissa of a canonical fixed-point string
+ * MUST match on-chain `parse_fixed_point` for values that passed `validate_response`
+ */
+function parseFixedPoint(value: string, decimals: number): bigint | null {
+  const match = /^(-?)(0|[1-9][0-9]*)(?:\.([0-9]+))?$/.exec(value);
+  if (!match) return null;
+  const frac = match[3] ?? "";
+  if (frac.length !== decimals) return null;
+  return BigInt(match[1] + match[2] + frac);
+}
+
+/**
+ * MUST match on-chain `numeric_value`: Score and Decimal only
+ */
+function numericValue(format: Format, spec: ResponseSpec, value: string): bigint | null {
+  if (format.score) return parseFixedPoint(value, 0);
+  if (format.decimal) return parseFixedPoint(value, spec.decimals);
+  return null;
+}
+
+/**
+ * Order `tally_votes` expects a field's buckets in
+ * MUST match on-chain `option_key`: numbers as sign-flipped big-endian i128 in the
+ * first 16 bytes, anything else by keccak256 of the value
+ */
+function optionKey(format: Format, spec: ResponseSpec, value: string): Buffer {
+  const number = numericValue(format, spec, value);
+  if (number === null) {
+    return Buffer.from(keccak_256(Buffer.from(value, "utf-8")));
+  }
+  const flipped = BigInt.asUintN(128, number) ^ (BigInt(1) << BigInt(127));
+  const key = Buffer.alloc(32);
+  key.write(flipped.toString(16).padStart(32, "0"), 0, "hex");
+  return key;
+}
+
+function fieldFormat(data: QueryData, field: number): { format: Format; spec: ResponseSpec } {
+  return field === WHOLE_ANSWER ? { format: data.format, spec: data.spec } : data.fields[field - 1];
+}
+
+/**
+ * Fields a tally stage walks, in order
+ * MUST match on-chain `tally_field`
+ */
+function stageFields(data: QueryData, settle: boolean): number[] {
+  if (!data.format.vector) return [WHOLE_ANSWER];
+  const fields = data.fields.map((_, i) => i + 1);
+  return settle ? [...fields, WHOLE_ANSWER] : fields;
+}
+
+/**
+ * Buckets `tally_votes` still has to see, from its saved cursor to the end of the tally
+ * A scan runs straight into the settle pass, so both are listed.
+ */
+function remainingBuckets(data: QueryData, tally: TallyProgress, buckets: Bucket[]): Bucket[] {
+  const byField = (field: number) =>
+    buckets.filter((b) => b.field === field).sort((a, b) => Buffer.compare(a.key, b.key));
+
+  const settling = !!tally.stage.settle;
+  const cursor = tally.stage.pending ? { step: 0, processed: 0 } : tally;
+  const remaining: Bucket[] = [];
+  stageFields(data, settling).forEach((field, step) => {
+    if (step < cursor.step) return;
+    remaining.push(...byField(field).slice(step === cursor.step ? cursor.processed : 0));
+  });
+  if (!settling) {
+    stageFields(data, true).forEach((field) => remaining.push(...byField(field)));
+  }
+  return remaining;
+}
+
+/**
+ * MUST match on-chain `is_correct_field`: numbers within the tolerance, exact match otherwise
+ */
+function isCorrectField(format: Format, spec: ResponseSpec, typed: TypedResult, result: string, value: string): boolean {
+  if (!typed.kind.number) return value === result;
+  const answer = numericValue(format, spec, value);
+  if (answer === null) return false;
+  const diff = answer - BigInt(typed.value.toString());
+  return (diff < 0 ? -diff : diff) <= BigInt(spec.tolerance.toString());
+}
+
+/**
+ * MUST match on-chain `is_correct_answer`: a vector answer needs every field right
+ */
+function isCorrectAnswer(data: QueryData, value: string): boolean {
+  if (!data.format.vector) {
+    return isCorrectField(data.format, data.spec, data.typedResult, data.result, value);
+  }
+  const parts = value.split(VECTOR_SEPARATOR);
+  return (
+    parts.length === data.fieldResults.length &&
+    parts.every((part, i) =>
+      isCorrectField(data.fields[i].format, data.fields[i].spec, data.fieldResults[i].typedResult, data.fieldResults[i].value, part)
+    )
+  );
+}
+
+/**
+ * Hash of the earliest slot at or after `target` in raw SlotHashes data, null if aged out
+ * MUST match on-chain `first_slot_hash_since`
+ */
+function firstSlotHashSince(data: Buffer, target: bigint): Buffer | null {
+  const count = Number(data.readBigUInt64LE(0));
+  let found: Buffer | null = null;
+  for (let i = 0; i < count; i++) {
+    const offset = 8 + i * 40;
+    const slot = data.readBigUInt64LE(offset);
+    if (slot < target) return found;
+    found = data.subarray(offset + 8, offset + 40);
+    if (slot === target) return found;
+  }
+  return null;
+}
+
+/**
+ * Current round's option buckets of a query
+ */
+async function fetchBuckets(program: Program, query: PublicKey, data: QueryData): Promise<Bucket[]> {
+  const accounts = await (program.account as any).optionBucket.all([
+    { memcmp: { offset: 8, bytes: query.toBase58() } },
+  ]);
+  return accounts
+    .filter((a: any) => a.account.round === data.round && a.account.roundSlot.eq(data.roundSlot))
+    .map((a: any) => {
+      const { format, spec } = fieldFormat(data, a.account.field);
+      return {
+        pubkey: a.publicKey,
+        field: a.account.field,
+        value: a.account.value,
+        weight: a.account.weight,
+        ticketOffset: a.account.ticketOffset,
+        key: optionKey(format, spec, a.account.value),
+      };
+    });
+}
+
+function loadWallet(path: string): Keypair {
+  return Keypair.fromSecretKey(new Uint8Array(JSON.parse(fs.readFileSync(path, "utf-8"))));
+}
+
+function derivePDAs(programId: PublicKey, query: PublicKey, categoryId: string) {
+  const [config] = PublicKey.findProgramAddressSync([Buffer.from("config")], programId);
+  const [categoryStats] = PublicKey.findProgramAddressSync(
+    [Buffer.from("category"), Buffer.from(categoryId)],
+    programId
+  );
+  const [voteStats] = PublicKey.findProgramAddressSync([Buffer.from("stats"), query.toBuffer()], programId);
+  const [queryOptions] = PublicKey.findProgramAddressSync([Buffer.from("options"), query.toBuffer()], programId);
+  return { config, categoryStats, voteStats, queryOptions };
+}
+
+// ============================================
+// MAIN KEEPER
+// ============================================
+
+async function main() {
+  const connection = new Connection(RPC_URL, "confirmed");
+  const keypair = loadWallet(WALLET_PATH);
+  const provider = new AnchorProvider(connection, new Wallet(keypair), {
+    commitment: "confirmed",
+  });
+  const program = new Program(IDL as any, provider);
+
+  console.log("Tally Keeper Active");
+  console.log(`   Address: ${keypair.publicKey.toBase58()}`);
+  console.log("");
+
+  // Main loop - intentional infinite loop for daemon process
+  // eslint-disable-next-line no-constant-condition
+  while (true) {
+    try {
+      await runTallyCycle(program);
+      await runLotteryCycle(program, connection);
+    } catch (e) {
+      console.error("Cycle Error:", e);
+    }
+
+    await sleep(CONFIG.pollIntervalMs);
+  }
+}
+
+// ============================================
+// TALLY CYCLE
+// ============================================
+
+async function runTallyCycle(program: Program) {
+  console.log("Scanning for queries to tally...");
+
+  const queries = await (program.account as any).queryAccount.all();
+  const now = Math.floor(Date.now() / 1000);
+
+  for (const query of queries) {
+    let data = query.account as unknown as QueryData;
+    const ready = (data.status.revealPhase && now > data.revealDeadline.toNumber()) || data.status.settling;
+    if (!ready) continue;
+
+    console.log(`  Tallying: ${data.uniqueEventId}`);
+    const { config, categoryStats, voteStats, queryOptions } = derivePDAs(
+      program.programId,
+      query.publicKey,
+      data.categoryId
+    );
+
+    try {
+      const buckets = await fetchBuckets(program, query.publicKey, data);
+      for (let call = 0; call < CONFIG.maxTallyCalls; call++) {
+        const stats = await (program.account as any).voteStatsSafe.fetch(voteStats);
+        const page: AccountMeta[] = remainingBuckets(data, stats.tally, buckets)
+          .slice(0, CONFIG.bucketsPerTx)
+          .map((b) => ({ pubkey: b.pubkey, isWritable: b.field === WHOLE_ANSWER, isSigner: false }));
+
+        await program.methods
+          .tallyVotes()
+          .accounts({
+            config,
+            queryAccount: query.publicKey,
+            categoryStats,
+            voteStats,
+            queryOptions: data.hasOptionLabels ? queryOptions : null,
+          })
+          .remainingAccounts(page)
+          .rpc();
+
+        data = (await (program.account as any).queryAccount.fetch(query.publicKey)) as QueryData;
+        if (!data.status.revealPhase && !data.status.settling) break;
+      }
+      console.log(`  Status: ${Object.keys(data.status)[0]}, result: ${data.result}`);
+    } catch (e: any) {
+      console.error(`  Tally failed: ${e.message}`);
+    }
+  }
+}
+
+// ============================================
+// LOTTERY CYCLE
+// ============================================
+
+async function runLotteryCycle(program: Program, connection: Connection) {
+  console.log("Scanning for lottery draws...");
+
+  const queries = await (program.account as any).queryAccount.all();
+  const currentSlot = await connection.getSlot();
+
+  for (const query of queries) {
+    const data = query.account as unknown as QueryData;
+    if (!data.status.finalized || data.lotterySlot.isZero()) continue;
+    if (currentSlot <= data.lotterySlot.toNumber()) continue;
+
+    console.log(`  Drawing: ${data.uniqueEventId}`);
+    try {
+      // Same seed as `draw_lottery`: reveal accumulator, slot hash, query
+      const sysvar = await connection.getAccountInfo(SYSVAR_SLOT_HASHES_PUBKEY);
+      if (!sysvar) throw new Error("SlotHashes unavailable");
+      const slotHash = firstSlotHashSince(sysvar.data, BigInt(data.lotterySlot.toString()));
+      if (!slotHash) {
+        // Aged out: `draw_lottery` re-arms the draw for a later slot
+        await program.methods
+          .drawLottery()
+          .accounts({
+            queryAccount: query.publicKey,
+            optionBucket: null,
+            slotHashes: SYSVAR_SLOT_HASHES_PUBKEY,
+          })
+          .rpc();
+        console.log(`  Lottery slot aged out, re-armed`);
+        continue;
+      }
+      const seed = keccak_256(Buffer.concat([Buffer.from(data.randomAccumulator), slotHash, query.publicKey.toBuffer()]));
+      const random = Buffer.from(seed).readBigUInt64LE(0);
+      const ticket = (random % BigInt(data.winningWeight.toString())) + BigInt(1);
+
+      // `tally_votes` laid the correct whole answers' tickets out one after another
+      const buckets = await fetchBuckets(program, query.publicKey, data);
+      const winner = buckets.find((b) => {
+        if (b.field !== WHOLE_ANSWER || !isCorrectAnswer(data, b.value)) return false;
+        const offset = BigInt(b.ticketOffset.toString());
+        return ticket > offset && ticket <= offset + BigInt(b.weight.toString());
+      });
+      if (!winner) throw new Error(`No bucket holds ticket ${ticket}`);
+
+      await program.methods
+        .drawLottery()
+        .accounts({
+          queryAccount: query.publicKey,
+          optionBucket: winner.pubkey,
+          slotHashes: SYSVAR_SLOT_HASHES_PUBKEY,
+        })
+        .rpc();
+
+      console.log(`  Ticket ${ticket} drawn in "${winner.value}"`);
+    } catch (e: any) {
+      console.error(`  Draw failed: ${e.message}`);
+    }
+  }
+}
+
+// ============================================
+// HELPERS
+// ============================================
+
+function sleep(ms: number): Promise<void> {
+  return new Promise((resolve) => setTimeout(resolve, ms));
+}
+
+// ============================================
+// ENTRY POINT
+// ============================================
+
+main().catch(console.error);
@@ -15,6 +15,9 @@ import {
   UserBet,
   getMarketPDA,
   getUserBetPDA,
+  getConfigPDA,
+  getCategoryPDA,
+  readQueryCategoryId,
 } from '../types/market';
 
 // Instruction discriminators (first 8 bytes of sha256 hash of instruction name)
@@ -205,6 +208,18 @@ export function useMarket() {
 
     try {
       const [userBetPDA] = getUserBetPDA(marketPubkey, publicKey);
+      const market = markets.find((m) => m.publicKey.equals(marketPubkey));
+      if (!market) {
+        throw new Error('Market not loaded');
+      }
+
+      // Betting stops while the oracle query's category is paused
+      const queryInfo = await connection.getAccountInfo(market.oracleQuery);
+      if (!queryInfo) {
+        throw new Error('Oracle query not found');
+      }
+      const [configPDA] = getConfigPDA();
+      const [categoryPDA] = getCategoryPDA(readQueryCategoryId(queryInfo.data));
 
       // Build instruction data
       const data = Buffer.alloc(8 + 8 + 1);
@@ -215,7 +230,10 @@ export function useMarket() {
       const instruction = new TransactionInstruction({
         keys: [
           { pubkey: publicKey, isSigner: true, isWritable: true },
+          { pubkey: configPDA, isSigner: false, isWritable: false },
           { pubkey: marketPubkey, isSigner: false, isWritable: true },
+          { pubkey: market.oracleQuery, isSigner: false, isWritable: false },
+          { pubkey: categoryPDA, isSigner: false, isWritable: false },
           { pubkey: userBetPDA, isSigner: false, isWritable: true },
           { pubkey: SystemProgram.programId, isSigner: false, isWritable: false },
         ],
@@ -242,7 +260,7 @@ export function useMarket() {
     } finally {
       setLoading(false);
     }
-  }, [publicKey, connection, signAndSend, fetchMarkets]);
+  }, [publicKey, connection, signAndSend, fetchMarkets, markets]);
 
   // Sell bet instruction
   const sellBet = useCallback(async (
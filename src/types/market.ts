@@ -53,6 +53,11 @@ export interface QueryAccount {
 export const MARKET_SEED = 'market';
 export const USER_BET_SEED = 'user_bet';
 export const QUERY_SEED = 'query';
+export const CONFIG_SEED = 'config';
+export const CATEGORY_SEED = 'category';
+
+// QueryAccount: discriminator, then the fixed-size typed_result (kind u8, value i128, exponent i8)
+const QUERY_STRINGS_OFFSET = 8 + 1 + 16 + 1;
 
 // Helper to derive market PDA
 export function getMarketPDA(
@@ -86,6 +91,29 @@ export function getUserBetPDA(
   );
 }
 
+export function getConfigPDA(programId: PublicKey = PROGRAM_ID): [PublicKey, number] {
+  return PublicKey.findProgramAddressSync([Buffer.from(CONFIG_SEED)], programId);
+}
+
+export function getCategoryPDA(
+  categoryId: string,
+  programId: PublicKey = PROGRAM_ID
+): [PublicKey, number] {
+  return PublicKey.findProgramAddressSync(
+    [Buffer.from(CATEGORY_SEED), Buffer.from(categoryId)],
+    programId
+  );
+}
+
+// Read category_id from raw QueryAccount data (it follows unique_event_id)
+export function readQueryCategoryId(data: Buffer): string {
+  let offset = QUERY_STRINGS_OFFSET;
+  offset += 4 + data.readUInt32LE(offset);
+  const categoryIdLen = data.readUInt32LE(offset);
+  offset += 4;
+  return data.slice(offset, offset + categoryIdLen).toString('utf8');
+}
+
 // Display helpers
 export function formatSol(lamports: number): string {
   return (lamports / 1_000_000_000).toFixed(2);